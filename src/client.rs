@@ -1,9 +1,12 @@
+use std::collections::{HashMap, HashSet};
 use std::io::{self, stdin, BufRead};
+use std::marker::PhantomData;
 use std::ops::ControlFlow;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use crossbeam_channel::{bounded, select, Receiver};
@@ -16,13 +19,20 @@ use iceoryx2::{
     sample::Sample as IpcSample,
 };
 
+use crate::codec::Codec;
 use crate::common::*;
 use crate::events::IpcEvent;
 use crate::messages::*;
 
 const DEADLINE: Duration = Duration::from_secs(30);
 
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// How long a single request is allowed to sit unanswered before the client
+/// gives up on it and asks the server to cancel it, rather than waiting
+/// forever. Checked every `DEADLINE_CHECK_INTERVAL`.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn run<C: Codec>() -> Result<(), Box<dyn std::error::Error>> {
     let (ctrlc_tx, ctrlc_rx) = bounded(0);
     ctrlc::set_handler(move || {
         println!("Ctrl+C pressed!");
@@ -35,12 +45,13 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let stdin_rx = spawn_stdin_chan();
 
     let node = NodeBuilder::new().create::<ipc::Service>()?;
-    let ipc_client = IpcClient::new(&node, stdin_rx, ctrlc_rx)?;
+    let ipc_client = IpcClient::<C>::new(&node, stdin_rx, ctrlc_rx)?;
 
     let waitset = WaitSetBuilder::new()
         .signal_handling_mode(SignalHandlingMode::Disabled)
         .create::<ipc::Service>()?;
     let client_guard = waitset.attach_notification(&ipc_client)?;
+    let deadline_check_guard = waitset.attach_interval(DEADLINE_CHECK_INTERVAL)?;
 
     let mut on_event = |attachment_id: WaitSetAttachmentId<ipc::Service>| {
         if attachment_id.has_event_from(&client_guard) {
@@ -48,6 +59,8 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                 Ok(ControlFlow::Break(_)) => return CallbackProgression::Stop,
                 _ => (),
             }
+        } else if attachment_id.has_event_from(&deadline_check_guard) {
+            let _ = ipc_client.check_deadlines();
         } else if attachment_id.has_missed_deadline(&client_guard) {
             println!(
                 "⚠️ The server did not respond a message for {:?}.",
@@ -66,30 +79,41 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[derive(Debug)]
-struct IpcClient {
+struct IpcClient<C> {
     // IPC
     publisher: Publisher<ipc::Service, [u8], ()>,
     subscriber: Subscriber<ipc::Service, [u8], ()>,
     listener: Listener<ipc::Service>,
     notifier: Notifier<ipc::Service>,
 
+    // Identity
+    client_id: ClientId,
+
     // State
     is_server_running: AtomicBool,
+    next_request_id: AtomicU64,
+    pending: Mutex<HashSet<u64>>,
+    // In-progress chunked reassembly buffers, keyed by correlation id.
+    chunks: Mutex<HashMap<u64, Vec<u8>>>,
+    // Deadline for each in-flight request, keyed by correlation id.
+    deadlines: Mutex<HashMap<u64, Instant>>,
 
     // User input
     stdin_rx: Receiver<io::Result<String>>,
     ctrlc_rx: Receiver<()>,
+
+    codec: PhantomData<C>,
 }
 
-impl FileDescriptorBased for IpcClient {
+impl<C> FileDescriptorBased for IpcClient<C> {
     fn file_descriptor(&self) -> &FileDescriptor {
         self.listener.file_descriptor()
     }
 }
 
-impl SynchronousMultiplexing for IpcClient {}
+impl<C> SynchronousMultiplexing for IpcClient<C> {}
 
-impl IpcClient {
+impl<C: Codec> IpcClient<C> {
     fn new(
         node: &Node<ipc::Service>,
         stdin_rx: Receiver<io::Result<String>>,
@@ -127,19 +151,42 @@ impl IpcClient {
             .allocation_strategy(AllocationStrategy::PowerOfTwo)
             .create()?;
 
-        notifier.notify_with_custom_event_id(IpcEvent::ClientConnected.into())?;
-
-        Ok(Self {
+        let client = Self {
             publisher,
             subscriber,
             listener,
             notifier,
 
+            client_id: ClientId(std::process::id()),
+
             is_server_running: AtomicBool::new(false),
+            next_request_id: AtomicU64::new(0),
+            pending: Mutex::new(HashSet::new()),
+            chunks: Mutex::new(HashMap::new()),
+            deadlines: Mutex::new(HashMap::new()),
 
             stdin_rx,
             ctrlc_rx,
-        })
+
+            codec: PhantomData,
+        };
+
+        client
+            .notifier
+            .notify_with_custom_event_id(IpcEvent::ClientConnected.into())?;
+        client.announce(RequestPayload::Connect)?;
+
+        Ok(client)
+    }
+
+    fn announce(&self, payload: RequestPayload) -> Result<()> {
+        let request = Request {
+            client_id: self.client_id,
+            id: u64::MAX,
+            payload,
+        };
+        let bytes = C::encode(&request)?;
+        self.send(&bytes)
     }
 
     fn handle_event(&self) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
@@ -163,32 +210,43 @@ impl IpcClient {
                     if let Ok(Some(sample)) = self.receive() {
                         println!("RESP received: len = {}", sample.payload().len());
 
-                        let response =
-                            bincode::deserialize::<ResponseRef>(sample.payload()).unwrap();
-
-                        match response {
-                            ResponseRef::FileSize(size) => {
-                                println!("File size = {}", size);
-                            }
-                            ResponseRef::FileContent(data) => {
-                                println!("File content: len = {}", data.len());
-                                println!("Head: {:02X?}", &data[..data.len().min(8)]);
-                            }
+                        match C::decode::<ResponseRef>(sample.payload()) {
+                            Ok(response) => self.deliver(response),
+                            Err(err) => println!(
+                                "⚠️ Failed to decode response with codec {:?} (server using a different --codec?): {}",
+                                C::NAME,
+                                err
+                            ),
                         }
                     }
                 }
                 IpcEvent::ServerReady => {
                     self.is_server_running.store(true, Ordering::SeqCst);
+
+                    // The server sends this once per response it sees us
+                    // receive, not just once per round, so several can land
+                    // while a round's requests are still outstanding — only
+                    // prompt for a new one once they've all resolved.
+                    if !self.pending.lock().unwrap().is_empty() {
+                        continue;
+                    }
+
                     println!("\nPlease input the path to file:");
                     if let Some(input) = read_line(&self.stdin_rx, &self.ctrlc_rx) {
                         let path = PathBuf::from(input);
-                        let request = Request::GetFileContent { path };
-                        let bytes = bincode::serialize(&request).unwrap();
-                        let _ = self.send(&bytes);
+                        let _ = self.send_request(RequestPayload::GetFileSize {
+                            path: path.clone(),
+                        });
+                        let _ = self.send_request(RequestPayload::GetFileContent { path });
                     } else {
                         return Ok(ControlFlow::Break(()));
                     }
                 }
+                IpcEvent::ResponseStreamEnd => {
+                    // Completion is actually driven by the `last` flag on
+                    // the final chunk (see `deliver`); this event is just a
+                    // diagnostic nudge that a stream has fully landed.
+                }
                 _ => (),
             }
         }
@@ -197,6 +255,117 @@ impl IpcClient {
         Ok(ControlFlow::Continue(()))
     }
 
+    fn send_request(&self, payload: RequestPayload) -> Result<()> {
+        let id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        self.pending.lock().unwrap().insert(id);
+        self.deadlines
+            .lock()
+            .unwrap()
+            .insert(id, Instant::now() + REQUEST_TIMEOUT);
+
+        let request = Request {
+            client_id: self.client_id,
+            id,
+            payload,
+        };
+        let bytes = C::encode(&request)?;
+        self.send(&bytes)
+    }
+
+    fn deliver(&self, response: ResponseRef) {
+        if response.client_id != self.client_id {
+            return;
+        }
+
+        let id = response.id;
+        match response.payload {
+            ResponsePayloadRef::FileSize(size) => {
+                self.resolve(id, ResponsePayload::FileSize(size));
+            }
+            ResponsePayloadRef::FileChunk { data, last, .. } => {
+                let mut chunks = self.chunks.lock().unwrap();
+                chunks.entry(id).or_default().extend_from_slice(data);
+
+                if last {
+                    let content = chunks.remove(&id).unwrap_or_default();
+                    drop(chunks);
+                    self.resolve(
+                        id,
+                        ResponsePayload::FileChunk {
+                            offset: 0,
+                            data: content,
+                            last: true,
+                        },
+                    );
+                } else {
+                    // REQUEST_TIMEOUT bounds the gap between chunks, not the
+                    // whole transfer, so a large file streaming steadily
+                    // doesn't get cancelled out from under it.
+                    self.deadlines
+                        .lock()
+                        .unwrap()
+                        .insert(id, Instant::now() + REQUEST_TIMEOUT);
+                }
+            }
+            ResponsePayloadRef::Error(message) => {
+                self.chunks.lock().unwrap().remove(&id);
+                self.resolve(id, ResponsePayload::Error(message.to_string()));
+            }
+        }
+    }
+
+    fn resolve(&self, id: u64, payload: ResponsePayload) {
+        self.deadlines.lock().unwrap().remove(&id);
+        if self.pending.lock().unwrap().remove(&id) {
+            print_response(&Response {
+                client_id: self.client_id,
+                id,
+                payload,
+            });
+        } else {
+            println!("⚠️ Received response for unknown request #{}", id);
+        }
+    }
+
+    /// Expires any requests whose deadline has passed: drops their pending
+    /// entry (so the caller waiting on it gives up instead of blocking
+    /// forever) and tells the server to stop working on them.
+    fn check_deadlines(&self) -> Result<()> {
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .deadlines
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            self.deadlines.lock().unwrap().remove(&id);
+            self.chunks.lock().unwrap().remove(&id);
+            self.pending.lock().unwrap().remove(&id);
+
+            println!(
+                "⚠️ [#{}] Request timed out after {:?}; cancelling.",
+                id, REQUEST_TIMEOUT
+            );
+
+            let request = Request {
+                client_id: self.client_id,
+                id,
+                payload: RequestPayload::Cancel,
+            };
+            let bytes = C::encode(&request)?;
+            self.send(&bytes)?;
+
+            self.notifier
+                .notify_with_custom_event_id(IpcEvent::RequestCancelled.into())?;
+        }
+
+        Ok(())
+    }
+
     fn send(&self, data: &[u8]) -> Result<()> {
         println!("📤 Client send {}", data.len());
         let sample = self.publisher.loan_slice_uninit(data.len())?;
@@ -222,14 +391,34 @@ impl IpcClient {
     }
 }
 
-impl Drop for IpcClient {
+impl<C: Codec> Drop for IpcClient<C> {
     fn drop(&mut self) {
+        let _ = self.announce(RequestPayload::Disconnect);
         let _ = self
             .notifier
             .notify_with_custom_event_id(IpcEvent::ClientDisconnected.into());
     }
 }
 
+fn print_response(response: &Response) {
+    match &response.payload {
+        ResponsePayload::FileSize(size) => {
+            println!("[#{}] File size = {}", response.id, size);
+        }
+        ResponsePayload::FileChunk { data, .. } => {
+            println!("[#{}] File content: len = {}", response.id, data.len());
+            println!(
+                "[#{}] Head: {:02X?}",
+                response.id,
+                &data[..data.len().min(8)]
+            );
+        }
+        ResponsePayload::Error(message) => {
+            println!("[#{}] ⚠️ Server returned an error: {}", response.id, message);
+        }
+    }
+}
+
 // ===== User input & Signal handling ===== //
 
 fn spawn_stdin_chan() -> Receiver<io::Result<String>> {
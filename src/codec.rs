@@ -0,0 +1,59 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Wire format used to encode/decode `Request`/`Response` envelopes. Picked
+/// at startup via the `--codec` flag in `main.rs`.
+pub trait Codec {
+    const NAME: &'static str;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>>;
+
+    /// `T` may borrow from `bytes` for codecs that support it — this is what
+    /// keeps the `ResponseRef` fast path zero-copy.
+    fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PostcardCodec;
+
+impl Codec for PostcardCodec {
+    const NAME: &'static str = "postcard";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    const NAME: &'static str = "messagepack";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec(value)?)
+    }
+
+    fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
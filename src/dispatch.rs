@@ -0,0 +1,101 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::messages::{RequestPayload, ResponsePayload};
+
+/// Zero-sized marker describing how to pull an operation's params out of a
+/// `RequestPayload`, so a handler can be registered as `on::<GetFileSize, _, _>(...)`.
+pub trait Operation {
+    type Params;
+
+    /// Hands `payload` back unchanged if it's not this operation's.
+    fn extract(payload: RequestPayload) -> Result<Self::Params, RequestPayload>;
+}
+
+pub struct GetFileSize;
+
+impl Operation for GetFileSize {
+    type Params = PathBuf;
+
+    fn extract(payload: RequestPayload) -> Result<PathBuf, RequestPayload> {
+        match payload {
+            RequestPayload::GetFileSize { path } => Ok(path),
+            other => Err(other),
+        }
+    }
+}
+
+pub struct GetFileContent;
+
+impl Operation for GetFileContent {
+    type Params = PathBuf;
+
+    fn extract(payload: RequestPayload) -> Result<PathBuf, RequestPayload> {
+        match payload {
+            RequestPayload::GetFileContent { path } => Ok(path),
+            other => Err(other),
+        }
+    }
+}
+
+type Handler = Box<dyn Fn(RequestPayload) -> Result<ResponsePayload, RequestPayload> + Send + Sync>;
+
+/// Tries each registered handler in turn until one claims the payload.
+pub struct Dispatcher {
+    handlers: Vec<Handler>,
+}
+
+impl fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl Dispatcher {
+    pub fn builder() -> DispatcherBuilder {
+        DispatcherBuilder { handlers: Vec::new() }
+    }
+
+    /// Falls back to a `ResponsePayload::Error` if no handler claims `payload`.
+    pub fn dispatch(&self, mut payload: RequestPayload) -> ResponsePayload {
+        for handler in &self.handlers {
+            payload = match handler(payload) {
+                Ok(response) => return response,
+                Err(payload) => payload,
+            };
+        }
+        ResponsePayload::Error("no handler registered for this request".to_string())
+    }
+}
+
+pub struct DispatcherBuilder {
+    handlers: Vec<Handler>,
+}
+
+impl DispatcherBuilder {
+    pub fn on<Op, F, R>(mut self, handler: F) -> Self
+    where
+        Op: Operation,
+        F: Fn(Op::Params) -> Result<R> + Send + Sync + 'static,
+        R: Into<ResponsePayload>,
+    {
+        self.handlers.push(Box::new(move |payload| {
+            Op::extract(payload).map(|params| {
+                handler(params)
+                    .map(Into::into)
+                    .unwrap_or_else(|err| ResponsePayload::Error(err.to_string()))
+            })
+        }));
+        self
+    }
+
+    pub fn build(self) -> Dispatcher {
+        Dispatcher {
+            handlers: self.handlers,
+        }
+    }
+}
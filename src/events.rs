@@ -17,6 +17,9 @@ pub enum IpcEvent {
     ServerReady,
     ProcessDied,
 
+    ResponseStreamEnd,
+    RequestCancelled,
+
     Unknown = u32::MAX as usize,
 }
 
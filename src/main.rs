@@ -1,11 +1,22 @@
 mod client;
 mod server;
 
+mod codec;
 mod common;
+mod dispatch;
 mod events;
 mod messages;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+use codec::{BincodeCodec, Codec, MessagePackCodec, PostcardCodec};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CodecKind {
+    Bincode,
+    Postcard,
+    MessagePack,
+}
 
 #[derive(Parser)]
 #[command(about)]
@@ -13,13 +24,32 @@ struct Args {
     /// Run as server
     #[arg(long)]
     server: bool,
+
+    /// Wire format used to encode/decode requests and responses
+    #[arg(long, value_enum, default_value_t = CodecKind::Bincode)]
+    codec: CodecKind,
+}
+
+impl std::fmt::Display for CodecKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CodecKind::Bincode => BincodeCodec::NAME,
+            CodecKind::Postcard => PostcardCodec::NAME,
+            CodecKind::MessagePack => MessagePackCodec::NAME,
+        };
+        write!(f, "{name}")
+    }
 }
 
 fn main() {
     let args = Args::parse();
-    if args.server {
-        server::run().unwrap();
-    } else {
-        client::run().unwrap();
-    }
+    let result = match (args.server, args.codec) {
+        (true, CodecKind::Bincode) => server::run::<BincodeCodec>(),
+        (true, CodecKind::Postcard) => server::run::<PostcardCodec>(),
+        (true, CodecKind::MessagePack) => server::run::<MessagePackCodec>(),
+        (false, CodecKind::Bincode) => client::run::<BincodeCodec>(),
+        (false, CodecKind::Postcard) => client::run::<PostcardCodec>(),
+        (false, CodecKind::MessagePack) => client::run::<MessagePackCodec>(),
+    };
+    result.unwrap();
 }
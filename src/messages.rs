@@ -2,20 +2,76 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+/// Identifies a client on the shared broadcast bus so frames can be routed
+/// and filtered by recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClientId(pub u32);
+
 #[derive(Serialize, Deserialize)]
-pub enum Request {
+pub struct Request {
+    pub client_id: ClientId,
+    pub id: u64,
+    pub payload: RequestPayload,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RequestPayload {
+    /// Sent once when a client comes online, so the server can register it.
+    Connect,
+    /// Sent once from a client's `Drop`, so the server can deregister it.
+    Disconnect,
+    /// Tells the server to abandon work on the request identified by this
+    /// envelope's `id`, sent once that request has missed its deadline on
+    /// the client side.
+    Cancel,
     GetFileSize { path: PathBuf },
     GetFileContent { path: PathBuf },
 }
 
-#[derive(Serialize)]
-pub enum Response {
+/// Owned response envelope, built by the server when encoding a reply and by
+/// the client once it has finished decoding one off the wire.
+#[derive(Serialize, Deserialize)]
+pub struct Response {
+    pub client_id: ClientId,
+    pub id: u64,
+    pub payload: ResponsePayload,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ResponsePayload {
     FileSize(u64),
-    FileContent(Vec<u8>),
+    /// One window of a file's content; `last` marks the final chunk.
+    FileChunk { offset: u64, data: Vec<u8>, last: bool },
+    Error(String),
+}
+
+impl From<u64> for ResponsePayload {
+    fn from(size: u64) -> Self {
+        ResponsePayload::FileSize(size)
+    }
+}
+
+impl From<Vec<u8>> for ResponsePayload {
+    fn from(data: Vec<u8>) -> Self {
+        ResponsePayload::FileChunk {
+            offset: 0,
+            data,
+            last: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResponseRef<'a> {
+    pub client_id: ClientId,
+    pub id: u64,
+    #[serde(borrow)]
+    pub payload: ResponsePayloadRef<'a>,
 }
 
 #[derive(Deserialize)]
-pub enum ResponseRef<'a> {
+pub enum ResponsePayloadRef<'a> {
     FileSize(u64),
-    FileContent(&'a [u8]),
+    FileChunk { offset: u64, data: &'a [u8], last: bool },
+    Error(&'a str),
 }
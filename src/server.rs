@@ -1,5 +1,7 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
@@ -15,14 +17,20 @@ use iceoryx2::{
 
 const DEADLINE: Duration = Duration::from_secs(15);
 
+const CHUNK_SIZE: usize = 128 * 1024;
+
+const CANCELLED_TTL: Duration = Duration::from_secs(30);
+
+use crate::codec::Codec;
 use crate::common::*;
+use crate::dispatch::{Dispatcher, GetFileContent, GetFileSize};
 use crate::events::IpcEvent;
 use crate::messages::*;
 
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+pub fn run<C: Codec>() -> Result<(), Box<dyn std::error::Error>> {
     let node = NodeBuilder::new().create::<ipc::Service>()?;
 
-    let ipc_server = match IpcServer::new(&node) {
+    let ipc_server = match IpcServer::<C>::new(&node) {
         Ok(server) => server,
         Err(err) => {
             match err.downcast::<PublishSubscribeOpenOrCreateError>() {
@@ -46,7 +54,7 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         if attachment_id.has_event_from(&server_guard) {
             ipc_server.handle_event().unwrap();
         } else if attachment_id.has_missed_deadline(&server_guard) {
-            if !ipc_server.has_client.load(Ordering::SeqCst) {
+            if ipc_server.clients.lock().unwrap().is_empty() {
                 return CallbackProgression::Stop;
             }
 
@@ -65,24 +73,38 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Work postponed by `poll_for_cancel` while a response was mid-stream, to
+/// be replayed at the top of the next `handle_event`.
+#[derive(Debug)]
+enum DeferredEvent {
+    Request(ClientId, u64, RequestPayload),
+    Raw(IpcEvent),
+}
+
 #[derive(Debug)]
-struct IpcServer {
-    has_client: AtomicBool,
+struct IpcServer<C> {
+    clients: Mutex<HashMap<ClientId, Instant>>,
     subscriber: Subscriber<ipc::Service, [u8], ()>,
     publisher: Publisher<ipc::Service, [u8], ()>,
     notifier: Notifier<ipc::Service>,
     listener: Listener<ipc::Service>,
+
+    dispatcher: Dispatcher,
+    cancelled: Mutex<HashMap<(ClientId, u64), Instant>>,
+    deferred: Mutex<VecDeque<DeferredEvent>>,
+
+    codec: PhantomData<C>,
 }
 
-impl FileDescriptorBased for IpcServer {
+impl<C> FileDescriptorBased for IpcServer<C> {
     fn file_descriptor(&self) -> &FileDescriptor {
         self.listener.file_descriptor()
     }
 }
 
-impl SynchronousMultiplexing for IpcServer {}
+impl<C> SynchronousMultiplexing for IpcServer<C> {}
 
-impl IpcServer {
+impl<C: Codec> IpcServer<C> {
     fn new(node: &Node<ipc::Service>) -> Result<Self, Box<dyn std::error::Error>> {
         let c2s_service_name: ServiceName = C2S_SERVICE_NAME.try_into()?;
         let c2s_service = node
@@ -118,16 +140,38 @@ impl IpcServer {
         notifier.notify_with_custom_event_id(IpcEvent::ServerConnected.into())?;
         notifier.notify_with_custom_event_id(IpcEvent::ServerReady.into())?;
 
+        let dispatcher = Dispatcher::builder()
+            .on::<GetFileSize, _, _>(|path| Ok(std::fs::metadata(&path)?.len()))
+            .on::<GetFileContent, _, _>(|path| Ok(std::fs::read(&path)?))
+            .build();
+
         Ok(Self {
-            has_client: AtomicBool::new(false),
+            clients: Mutex::new(HashMap::new()),
             subscriber,
             publisher,
             listener,
             notifier,
+
+            dispatcher,
+            cancelled: Mutex::new(HashMap::new()),
+            deferred: Mutex::new(VecDeque::new()),
+
+            codec: PhantomData,
         })
     }
 
     fn handle_event(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.sweep_cancelled();
+
+        while let Some(deferred) = self.deferred.lock().unwrap().pop_front() {
+            match deferred {
+                DeferredEvent::Request(client_id, id, payload) => {
+                    self.dispatch_request(client_id, id, payload)?;
+                }
+                DeferredEvent::Raw(event) => self.handle_raw_event(event)?,
+            }
+        }
+
         while let Some(event) = self.listener.try_wait_one()? {
             let event: IpcEvent = event.into();
             match event {
@@ -135,46 +179,130 @@ impl IpcServer {
                     if let Ok(Some(sample)) = self.receive() {
                         println!("received: len = {}", sample.payload().len());
 
-                        let request = bincode::deserialize::<Request>(sample.payload()).unwrap();
-
-                        match request {
-                            Request::GetFileSize { path } => {
-                                let size = std::fs::metadata(&path)?.len();
-                                let response = Response::FileSize(size);
-                                let data = bincode::serialize(&response).unwrap();
-                                self.send(&data)?;
+                        let request = match C::decode::<Request>(sample.payload()) {
+                            Ok(request) => request,
+                            Err(err) => {
+                                println!(
+                                    "⚠️ Failed to decode request with codec {:?} (client using a different --codec?): {}",
+                                    C::NAME,
+                                    err
+                                );
+                                continue;
                             }
-                            Request::GetFileContent { path } => {
-                                let content = std::fs::read(&path)?;
-                                let response = Response::FileContent(content);
-                                let data = bincode::serialize(&response).unwrap();
-                                self.send(&data)?;
-                            }
-                        }
+                        };
+                        self.dispatch_request(request.client_id, request.id, request.payload)?;
                     }
                 }
-                IpcEvent::ClientConnected => {
-                    println!("new client connected");
-                    self.has_client.store(true, Ordering::SeqCst);
-                    self.publisher.update_connections().unwrap();
-                    self.notifier
-                        .notify_with_custom_event_id(IpcEvent::ServerReady.into())?;
-                }
-                IpcEvent::ClientDisconnected => {
-                    println!("client disconnected");
-                    self.has_client.store(false, Ordering::SeqCst);
-                }
-                IpcEvent::ResponseReceived => {
-                    self.notifier
-                        .notify_with_custom_event_id(IpcEvent::ServerReady.into())?;
-                }
-                _ => (),
+                event => self.handle_raw_event(event)?,
             }
         }
 
         Ok(())
     }
 
+    /// Handles a non-`RequestSent` event, whether it just came off the
+    /// listener or was deferred while a previous response was mid-stream.
+    fn handle_raw_event(&self, event: IpcEvent) -> Result<(), Box<dyn std::error::Error>> {
+        match event {
+            IpcEvent::ClientConnected => {
+                self.publisher.update_connections().unwrap();
+                self.notifier
+                    .notify_with_custom_event_id(IpcEvent::ServerReady.into())?;
+            }
+            IpcEvent::ClientDisconnected => {}
+            IpcEvent::ResponseReceived => {
+                self.notifier
+                    .notify_with_custom_event_id(IpcEvent::ServerReady.into())?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Handles one decoded request, whether it just came off the wire or was
+    /// deferred while a previous response was mid-stream (see
+    /// `poll_for_cancel`).
+    fn dispatch_request(
+        &self,
+        client_id: ClientId,
+        id: u64,
+        payload: RequestPayload,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match payload {
+            RequestPayload::Connect => {
+                println!("client {:?} connected", client_id);
+                self.clients.lock().unwrap().insert(client_id, Instant::now());
+            }
+            RequestPayload::Disconnect => {
+                println!("client {:?} disconnected", client_id);
+                self.clients.lock().unwrap().remove(&client_id);
+            }
+            RequestPayload::Cancel => {
+                println!("client {:?} cancelled request #{}", client_id, id);
+                self.cancelled
+                    .lock()
+                    .unwrap()
+                    .insert((client_id, id), Instant::now());
+            }
+            payload => {
+                if self.cancelled.lock().unwrap().remove(&(client_id, id)).is_some() {
+                    println!(
+                        "request #{} from {:?} was cancelled before it could be handled",
+                        id, client_id
+                    );
+                } else {
+                    let response = self.dispatcher.dispatch(payload);
+                    self.send_response(client_id, id, response)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops cancel markers older than `CANCELLED_TTL`; the common case is a
+    /// cancel arriving for a request that already finished and was never
+    /// going to be looked up again.
+    fn sweep_cancelled(&self) {
+        let now = Instant::now();
+        self.cancelled
+            .lock()
+            .unwrap()
+            .retain(|_, seen_at| now.saturating_duration_since(*seen_at) < CANCELLED_TTL);
+    }
+
+    /// Non-blockingly checks whether `(client_id, id)` has been cancelled
+    /// since this response started sending. Anything else seen along the
+    /// way — another client's request, a `ClientConnected` notification,
+    /// and so on — is stashed in `deferred` for the next `handle_event`
+    /// call, since we're in the middle of handling a different one right
+    /// now and must not drop it.
+    fn poll_for_cancel(&self, client_id: ClientId, id: u64) -> Result<bool> {
+        while let Some(event) = self.listener.try_wait_one()? {
+            let event: IpcEvent = event.into();
+            if event != IpcEvent::RequestSent {
+                self.deferred.lock().unwrap().push_back(DeferredEvent::Raw(event));
+                continue;
+            }
+
+            if let Ok(Some(sample)) = self.receive() {
+                if let Ok(request) = C::decode::<Request>(sample.payload()) {
+                    if request.client_id == client_id
+                        && request.id == id
+                        && matches!(request.payload, RequestPayload::Cancel)
+                    {
+                        return Ok(true);
+                    }
+                    self.deferred.lock().unwrap().push_back(DeferredEvent::Request(
+                        request.client_id,
+                        request.id,
+                        request.payload,
+                    ));
+                }
+            }
+        }
+        Ok(false)
+    }
+
     fn receive(
         &self,
     ) -> Result<Option<IpcSample<ipc::Service, [u8], ()>>, Box<dyn std::error::Error>> {
@@ -197,9 +325,70 @@ impl IpcServer {
             .notify_with_custom_event_id(IpcEvent::ResponseSent.into())?;
         Ok(())
     }
+
+    /// Re-splits an oversized `FileChunk` into `CHUNK_SIZE` windows so
+    /// transfer size isn't bounded by available shared memory; anything
+    /// else goes out as a single frame.
+    fn send_response(&self, client_id: ClientId, id: u64, payload: ResponsePayload) -> Result<()> {
+        match payload {
+            ResponsePayload::FileChunk { data: content, .. } if content.len() > CHUNK_SIZE => {
+                let mut offset = 0;
+                loop {
+                    if self.poll_for_cancel(client_id, id)? {
+                        println!(
+                            "request #{} from {:?} was cancelled mid-stream",
+                            id, client_id
+                        );
+                        return Ok(());
+                    }
+
+                    let end = (offset + CHUNK_SIZE).min(content.len());
+                    let last = end == content.len();
+
+                    let response = Response {
+                        client_id,
+                        id,
+                        payload: ResponsePayload::FileChunk {
+                            offset: offset as u64,
+                            data: content[offset..end].to_vec(),
+                            last,
+                        },
+                    };
+                    let data = C::encode(&response)?;
+                    self.send(&data)?;
+
+                    offset = end;
+                    if last {
+                        break;
+                    }
+                }
+
+                self.notifier
+                    .notify_with_custom_event_id(IpcEvent::ResponseStreamEnd.into())?;
+                Ok(())
+            }
+            payload => {
+                let is_chunk = matches!(payload, ResponsePayload::FileChunk { .. });
+
+                let response = Response {
+                    client_id,
+                    id,
+                    payload,
+                };
+                let data = C::encode(&response)?;
+                self.send(&data)?;
+
+                if is_chunk {
+                    self.notifier
+                        .notify_with_custom_event_id(IpcEvent::ResponseStreamEnd.into())?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
-impl Drop for IpcServer {
+impl<C> Drop for IpcServer<C> {
     fn drop(&mut self) {
         self.notifier
             .notify_with_custom_event_id(IpcEvent::ServerDisconnected.into())